@@ -13,9 +13,18 @@ pub use bevy_utils::tracing::{
 
 use bevy_app::{AppBuilder, Plugin};
 #[cfg(feature = "tracing-chrome")]
-use tracing_subscriber::fmt::{format::DefaultFields, FormattedFields};
+use tracing_subscriber::fmt::format::DefaultFields;
 
-use tracing_subscriber::{prelude::*, registry::Registry, EnvFilter};
+use tracing_subscriber::{
+    fmt::{
+        format::Writer,
+        time::FormatTime,
+        FmtContext, FormatEvent, FormatFields, FormattedFields,
+    },
+    prelude::*,
+    registry::{LookupSpan, Registry},
+    reload, EnvFilter,
+};
 use tracing_subscriber::{fmt, fmt::format};
 use std::sync::{Arc, RwLock};
 
@@ -34,6 +43,32 @@ pub struct LogSettings {
 
     /// String that is prepended to the main log message, which you can change with `LogSettings::set_dynamic_prefix`
     pub dynamic_prefix: Arc<RwLock<String>>,
+
+    /// If set, logs are additionally written to a rolling file on top of the usual stdout
+    /// output. Only used on targets other than wasm32 and Android; there it is ignored.
+    pub file_appender: Option<FileAppenderSettings>,
+
+    /// Collector endpoint spans are exported to when built with the `trace-otlp` feature.
+    pub otlp_endpoint: String,
+
+    /// Service name reported to the OpenTelemetry collector when built with the `trace-otlp`
+    /// feature.
+    pub otlp_service_name: String,
+
+    /// Controls how log lines are rendered.
+    pub format: LogFormat,
+
+    /// Whether to show the event's target (usually the originating module path).
+    pub show_target: bool,
+
+    /// Whether to show the source file and line number the event was logged from.
+    pub show_file_line: bool,
+
+    /// Whether to show the id of the thread the event was logged from.
+    pub show_thread_ids: bool,
+
+    /// Timer used to render each log line's timestamp.
+    pub timer: LogTimer,
 }
 
 impl Default for LogSettings {
@@ -42,6 +77,108 @@ impl Default for LogSettings {
             filter: "wgpu=error".to_string(),
             level: Level::INFO,
             dynamic_prefix: Arc::new(RwLock::new(String::new())),
+            file_appender: None,
+            otlp_endpoint: "http://localhost:4317".to_string(),
+            otlp_service_name: "bevy_app".to_string(),
+            format: LogFormat::Pretty,
+            show_target: true,
+            show_file_line: false,
+            show_thread_ids: false,
+            timer: LogTimer::SystemTime,
+        }
+    }
+}
+
+/// Timer used to render the timestamp on each log line, set via [`LogSettings::timer`].
+#[derive(Clone, Copy)]
+pub enum LogTimer {
+    /// Time elapsed since the subscriber was installed, rather than wall-clock time.
+    Uptime,
+    /// Wall-clock time (the default).
+    SystemTime,
+    /// Omit the timestamp entirely.
+    None,
+}
+
+/// A [`FormatTime`] that dispatches to whichever timer [`LogSettings::timer`] selected, so
+/// [`fmt::Layer::with_timer`] and [`PrefixedJsonFormat`] can share one concrete, runtime-selected
+/// timer type.
+#[derive(Clone, Copy)]
+enum LogTimerImpl {
+    Uptime(fmt::time::Uptime),
+    SystemTime(fmt::time::SystemTime),
+    None,
+}
+
+impl From<LogTimer> for LogTimerImpl {
+    fn from(timer: LogTimer) -> Self {
+        match timer {
+            LogTimer::Uptime => LogTimerImpl::Uptime(fmt::time::Uptime::default()),
+            LogTimer::SystemTime => LogTimerImpl::SystemTime(fmt::time::SystemTime),
+            LogTimer::None => LogTimerImpl::None,
+        }
+    }
+}
+
+impl FormatTime for LogTimerImpl {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        match self {
+            LogTimerImpl::Uptime(t) => t.format_time(w),
+            LogTimerImpl::SystemTime(t) => t.format_time(w),
+            LogTimerImpl::None => Ok(()),
+        }
+    }
+}
+
+/// Output format for [`LogSettings::format`].
+#[derive(Clone, Copy)]
+pub enum LogFormat {
+    /// Human-readable, one line per event (the default).
+    Pretty,
+    /// Like [`LogFormat::Pretty`], but for shorter lines: span names are left out, and the fields
+    /// of the entered spans follow the event's own fields.
+    Compact,
+    /// One JSON object per event, suitable for log-ingestion pipelines (ELK/Loki/etc).
+    Json,
+}
+
+/// How often [`FileAppenderSettings`] starts a new log file.
+#[derive(Clone)]
+pub enum Rollover {
+    /// Never roll over; all log output goes to a single file.
+    Never,
+    /// Start a new file every hour.
+    Hourly,
+    /// Start a new file every day.
+    Daily,
+}
+
+/// Settings for the optional rolling file output enabled via [`LogSettings::file_appender`].
+///
+/// With [`Rollover::Hourly`] or [`Rollover::Daily`], rolled files are named
+/// `prefix.YYYY-MM-DD-HH.suffix`/`prefix.YYYY-MM-DD.suffix` respectively, with the separating
+/// dot omitted for whichever of `prefix`/`suffix` is empty (e.g. a `suffix` of `"log"` produces
+/// editor-friendly `myapp.2021-01-01.log` names). With [`Rollover::Never`] there's no date
+/// component at all, and the file is just named `prefix.suffix`.
+#[derive(Clone)]
+pub struct FileAppenderSettings {
+    /// Directory the rolling log files are written into.
+    pub directory: String,
+    /// How often a new log file is started.
+    pub rotation: Rollover,
+    /// Prepended to the rolled filename.
+    pub prefix: String,
+    /// Appended to the rolled filename.
+    pub suffix: String,
+}
+
+impl Default for FileAppenderSettings {
+    fn default() -> Self {
+        Self {
+            directory: "logs".to_string(),
+            rotation: Rollover::Daily,
+            prefix: "log".to_string(),
+            suffix: "log".to_string(),
         }
     }
 }
@@ -55,43 +192,359 @@ impl LogSettings {
     }
 }
 
+/// A handle to the live [`EnvFilter`] backing [`LogPlugin`], inserted into the app's
+/// [`World`](bevy_ecs::world::World) as a resource so any system can change log verbosity
+/// without restarting the app.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// The most recently applied filter string, kept around so [`LogFilterHandle::set_level`]
+    /// can preserve its per-target (`target=level`) directives instead of discarding them.
+    directives: Arc<RwLock<String>>,
+}
+
+impl LogFilterHandle {
+    /// Parses `new` as an [`EnvFilter`] directive string and swaps it in, replacing whatever
+    /// filter was previously active.
+    pub fn set_filter(&self, new: &str) {
+        match EnvFilter::try_new(new) {
+            Ok(filter) => {
+                if self.handle.reload(filter).is_err() {
+                    bevy_utils::tracing::error!(
+                        "could not reload log filter; the tracing subscriber appears to have been dropped"
+                    );
+                    return;
+                }
+                *self.directives.write().expect("directives lock poisoned") = new.to_string();
+            }
+            Err(err) => bevy_utils::tracing::error!(
+                "could not parse `{}` as a log filter: {}",
+                new,
+                err
+            ),
+        }
+    }
+
+    /// Raises or lowers the global log level while preserving any per-target (`target=level`)
+    /// directives from the most recently applied filter, e.g. the default `wgpu=error` noise
+    /// suppression.
+    pub fn set_level(&self, level: Level) {
+        let per_target_directives = {
+            let directives = self.directives.read().expect("directives lock poisoned");
+            directives
+                .split(',')
+                .filter(|directive| directive.contains('='))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let new = if per_target_directives.is_empty() {
+            level.to_string()
+        } else {
+            format!("{},{}", level, per_target_directives)
+        };
+        self.set_filter(&new);
+    }
+}
+
+/// [`FormatEvent`] used for [`LogFormat::Json`].
+///
+/// This is modelled on [`tracing_subscriber`]'s own JSON formatter, but also emits `dynamic_prefix`
+/// as its own `"prefix"` field. Event fields are written as top-level keys, and each entry of
+/// `"spans"` holds the span's name and its fields rendered as one string under `"fields"`, not as
+/// separate JSON keys. The built-in formatter serializes event fields directly rather than
+/// through a [`FormatFields`] visitor, so the `fmt_fields`-based prefixing trick used for
+/// [`LogFormat::Pretty`]/[`LogFormat::Compact`] can't reach JSON output - hence this formatter.
+struct PrefixedJsonFormat {
+    dynamic_prefix: Arc<RwLock<String>>,
+    show_target: bool,
+    show_file_line: bool,
+    show_thread_ids: bool,
+    timer: LogTimerImpl,
+}
+
+impl<S, N> FormatEvent<S, N> for PrefixedJsonFormat
+where
+    S: bevy_utils::tracing::Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &bevy_utils::tracing::Event<'_>,
+    ) -> std::fmt::Result {
+        use serde::ser::{SerializeMap, Serializer as _};
+
+        let meta = event.metadata();
+        let mut timestamp = String::new();
+        self.timer.format_time(&mut Writer::new(&mut timestamp)).ok();
+
+        // span-scoped fields (e.g. from `info_span!(name, key = val)`), root-to-leaf; each span's
+        // fields are the string the layer's field formatter rendered for it
+        let spans = ctx.lookup_current().map(|leaf| {
+            leaf.scope()
+                .from_root()
+                .filter_map(|span| {
+                    span.extensions()
+                        .get::<FormattedFields<N>>()
+                        .map(|fields| JsonSpan {
+                            name: span.metadata().name(),
+                            fields: fields.fields.clone(),
+                        })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut visit = || {
+            let mut serializer = serde_json::Serializer::new(WriteAdaptor(&mut writer));
+            let mut serializer = serializer.serialize_map(None)?;
+            if !timestamp.is_empty() {
+                serializer.serialize_entry("timestamp", &timestamp)?;
+            }
+            serializer.serialize_entry("level", &meta.level().as_str())?;
+            if self.show_target {
+                serializer.serialize_entry("target", meta.target())?;
+            }
+            if self.show_file_line {
+                if let Some(file) = meta.file() {
+                    serializer.serialize_entry("file", file)?;
+                }
+                if let Some(line) = meta.line() {
+                    serializer.serialize_entry("line", &line)?;
+                }
+            }
+            if self.show_thread_ids {
+                serializer.serialize_entry(
+                    "threadId",
+                    &format!("{:?}", std::thread::current().id()),
+                )?;
+            }
+            if let Some(spans) = &spans {
+                if !spans.is_empty() {
+                    serializer.serialize_entry("spans", spans)?;
+                }
+            }
+            let prefix = self
+                .dynamic_prefix
+                .read()
+                .expect("dynamic_prefix log poisoned");
+            if !prefix.is_empty() {
+                serializer.serialize_entry("prefix", prefix.trim_end())?;
+            }
+            drop(prefix);
+            let mut visitor = tracing_serde::SerdeMapVisitor::new(serializer);
+            event.record(&mut visitor);
+            visitor.take_serializer()?.end()
+        };
+        visit().map_err(|_| std::fmt::Error)?;
+        writeln!(writer)
+    }
+}
+
+/// One entry of the `"spans"` array [`PrefixedJsonFormat`] emits for the current span scope.
+#[derive(serde::Serialize)]
+struct JsonSpan {
+    name: &'static str,
+    fields: String,
+}
+
+/// Bridges the [`fmt::Write`](std::fmt::Write) that [`Writer`] implements to the
+/// [`io::Write`](std::io::Write) that [`serde_json::Serializer`] needs.
+struct WriteAdaptor<'a, 'b>(&'a mut Writer<'b>);
+
+impl<'a, 'b> std::io::Write for WriteAdaptor<'a, 'b> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let s = std::str::from_utf8(buf)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        self.0
+            .write_str(s)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        Ok(s.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds a `fmt::Layer` honoring [`LogSettings::format`] and its field-selection toggles, for
+/// `writer`. Shared by the stdout layer and the rolling file layer so both respect the same
+/// configuration instead of the file output being hard-coded to one style.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+fn build_fmt_layer<S, W>(
+    log_format: LogFormat,
+    show_target: bool,
+    show_file_line: bool,
+    show_thread_ids: bool,
+    timer: LogTimerImpl,
+    dynamic_prefix: Arc<RwLock<String>>,
+    writer: W,
+) -> Box<dyn tracing_subscriber::Layer<S> + Send + Sync>
+where
+    S: bevy_utils::tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'writer> fmt::MakeWriter<'writer> + Send + Sync + 'static,
+{
+    if let LogFormat::Json = log_format {
+        return fmt::Layer::default()
+            .with_writer(writer)
+            .event_format(PrefixedJsonFormat {
+                dynamic_prefix,
+                show_target,
+                show_file_line,
+                show_thread_ids,
+                timer,
+            })
+            .boxed();
+    }
+
+    // when rendering the message field (the main bit of text in the info!(...) call) prepend
+    // the dynamic_prefix; every other field is printed as key=val
+    let formatter = format::debug_fn(move |writer, field, value| {
+        if field.name() == "message" {
+            write!(
+                writer,
+                "{}{:?}",
+                *dynamic_prefix.read().expect("extra lock poisoned"),
+                value
+            )
+        } else {
+            write!(writer, "{}={:?}", field, value)
+        }
+    })
+    .delimited(", ");
+    let layer = fmt::Layer::default()
+        .with_writer(writer)
+        .fmt_fields(formatter)
+        .with_target(show_target)
+        .with_file(show_file_line)
+        .with_line_number(show_file_line)
+        .with_thread_ids(show_thread_ids)
+        .with_timer(timer);
+    match log_format {
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => unreachable!("handled above"),
+    }
+}
+
+/// Non-send resource kept alive so the OTLP tracer provider installed by [`LogPlugin`] flushes
+/// and shuts down cleanly when the app exits.
+#[cfg(feature = "trace-otlp")]
+struct OtlpGuard(opentelemetry_sdk::trace::TracerProvider);
+
+#[cfg(feature = "trace-otlp")]
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.0.shutdown() {
+            bevy_utils::tracing::error!("failed to shut down OTLP tracer provider: {}", err);
+        }
+    }
+}
+
 impl Plugin for LogPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        let (default_filter, dynamic_prefix) = {
+        let (
+            default_filter,
+            dynamic_prefix,
+            file_appender_settings,
+            log_format,
+            show_target,
+            show_file_line,
+            show_thread_ids,
+            timer,
+        ) = {
             let settings = app
                 .world_mut()
                 .get_resource_or_insert_with(LogSettings::default);
-            (format!("{},{}", settings.level, settings.filter), settings.dynamic_prefix.clone())
+            (
+                format!("{},{}", settings.level, settings.filter),
+                settings.dynamic_prefix.clone(),
+                settings.file_appender.clone(),
+                settings.format,
+                settings.show_target,
+                settings.show_file_line,
+                settings.show_thread_ids,
+                LogTimerImpl::from(settings.timer),
+            )
+        };
+        #[cfg(feature = "trace-otlp")]
+        let (otlp_endpoint, otlp_service_name) = {
+            let settings = app.world().get_resource::<LogSettings>().unwrap();
+            (settings.otlp_endpoint.clone(), settings.otlp_service_name.clone())
         };
 
+        // `EnvFilter::try_from_default_env` prefers `RUST_LOG` over `default_filter` when it's
+        // set and parses successfully, so `LogFilterHandle` must track whichever string actually
+        // won - otherwise the first `set_filter`/`set_level` call clobbers a user's `RUST_LOG`
+        // directives with the `LogSettings` default instead of preserving them.
+        let initial_directives = std::env::var("RUST_LOG")
+            .ok()
+            .filter(|from_env| EnvFilter::try_new(from_env).is_ok())
+            .unwrap_or_else(|| default_filter.clone());
         let filter_layer = EnvFilter::try_from_default_env()
             .or_else(|_| EnvFilter::try_new(&default_filter))
             .unwrap();
 
+        // Wrap the filter in a reload layer so `LogFilterHandle` can swap it out later, and
+        // clone the handle out now - it must outlive the subscriber, which is moved into
+        // `set_global_default` below.
+        let (filter_layer, reload_handle) = reload::Layer::new(filter_layer);
+        app.world_mut().insert_resource(LogFilterHandle {
+            handle: reload_handle,
+            directives: Arc::new(RwLock::new(initial_directives)),
+        });
+
         let subscriber = Registry::default().with(filter_layer);
 
         #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
         {
-            // moving dynamic_prefix clone into closure
-            let dynamic_prefix = dynamic_prefix.clone();
-            let formatter = format::debug_fn(move |writer, field, value| {
-                // when rendering the message field (the main bit of text in the info!(...) call)
-                // prepend the dynamic_prefix
-                if field.name() == "message" {
-                    write!(writer, "{}{:?}", *dynamic_prefix.read().expect("extra lock poisoned"), value)
-                } else {
-                    // additional fields just printed as key=val
-                    write!(writer, "{}={:?}", field, value)
-                }
-            })
-            .delimited(", ");
-
-            // use default formatter, but replace field format with our custom one that prefixes message
-            let fmt_layer = fmt::Layer::default().fmt_fields(formatter);
+            let fmt_layer = build_fmt_layer(
+                log_format,
+                show_target,
+                show_file_line,
+                show_thread_ids,
+                timer,
+                dynamic_prefix.clone(),
+                std::io::stdout,
+            );
             let subscriber = subscriber.with(fmt_layer);
 
+            // when configured, also write logs to a rolling file - through the same
+            // format/field-selection logic as stdout above, so e.g. `LogFormat::Json` applies to
+            // the file output too; the background flushing thread's guard is kept alive as a
+            // non-send resource, same as the chrome guard below
+            let file_appender_layer = file_appender_settings.map(|settings| {
+                let rotation = match settings.rotation {
+                    Rollover::Never => tracing_appender::rolling::Rotation::NEVER,
+                    Rollover::Hourly => tracing_appender::rolling::Rotation::HOURLY,
+                    Rollover::Daily => tracing_appender::rolling::Rotation::DAILY,
+                };
+                let mut builder = tracing_appender::rolling::Builder::new().rotation(rotation);
+                if !settings.prefix.is_empty() {
+                    builder = builder.filename_prefix(&settings.prefix);
+                }
+                if !settings.suffix.is_empty() {
+                    builder = builder.filename_suffix(&settings.suffix);
+                }
+                let file_appender = builder
+                    .build(&settings.directory)
+                    .expect("could not build rolling file appender");
+                let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+                app.world_mut().insert_non_send(guard);
+                build_fmt_layer(
+                    log_format,
+                    show_target,
+                    show_file_line,
+                    show_thread_ids,
+                    timer,
+                    dynamic_prefix.clone(),
+                    non_blocking,
+                )
+            });
+            let subscriber = subscriber.with(file_appender_layer);
+
             #[cfg(feature = "tracing-chrome")]
-            {
+            let subscriber = {
                 let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
                     .name_fn(Box::new(|event_or_span| match event_or_span {
                         tracing_chrome::EventOrSpan::Event(event) => event.metadata().name().into(),
@@ -107,16 +560,41 @@ impl Plugin for LogPlugin {
                     }))
                     .build();
                 app.world_mut().insert_non_send(guard);
-                let subscriber = subscriber.with(chrome_layer);
-                bevy_utils::tracing::subscriber::set_global_default(subscriber)
-                    .expect("Could not set global default tracing subscriber. If you've already set up a tracing subscriber, please disable LogPlugin from Bevy's DefaultPlugins");
-            }
+                subscriber.with(chrome_layer)
+            };
 
-            #[cfg(not(feature = "tracing-chrome"))]
-            {
-                bevy_utils::tracing::subscriber::set_global_default(subscriber)
-                    .expect("Could not set global default tracing subscriber. If you've already set up a tracing subscriber, please disable LogPlugin from Bevy's DefaultPlugins");
-            }
+            // live frame profiling, independent of (and stackable with) the chrome trace layer above
+            #[cfg(feature = "tracing-tracy")]
+            let subscriber = subscriber.with(tracing_tracy::TracyLayer::new());
+
+            // pipe spans/events to an OpenTelemetry collector (Jaeger/Tempo/etc) for distributed
+            // tracing in server and networked multiplayer builds
+            #[cfg(feature = "trace-otlp")]
+            let subscriber = {
+                // Bevy apps don't run inside a Tokio executor by default, so a batch exporter
+                // (which needs one to drive background flushing) would panic for most
+                // consumers. Export spans synchronously on the calling thread instead.
+                let exporter = opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(otlp_endpoint)
+                    .build_span_exporter()
+                    .expect("could not build OTLP exporter");
+                let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+                    .with_simple_exporter(exporter)
+                    .with_config(opentelemetry_sdk::trace::config().with_resource(
+                        opentelemetry_sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+                            "service.name",
+                            otlp_service_name,
+                        )]),
+                    ))
+                    .build();
+                let tracer = opentelemetry::trace::TracerProvider::tracer(&provider, "bevy_log");
+                app.world_mut().insert_non_send(OtlpGuard(provider));
+                subscriber.with(tracing_opentelemetry::layer().with_tracer(tracer))
+            };
+
+            bevy_utils::tracing::subscriber::set_global_default(subscriber)
+                .expect("Could not set global default tracing subscriber. If you've already set up a tracing subscriber, please disable LogPlugin from Bevy's DefaultPlugins");
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -137,3 +615,26 @@ impl Plugin for LogPlugin {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_level_preserves_per_target_directives() {
+        let (_layer, handle) = reload::Layer::<EnvFilter, Registry>::new(EnvFilter::new(
+            "debug,wgpu=error",
+        ));
+        let filter_handle = LogFilterHandle {
+            handle,
+            directives: Arc::new(RwLock::new("debug,wgpu=error".to_string())),
+        };
+
+        filter_handle.set_level(Level::TRACE);
+
+        assert_eq!(
+            *filter_handle.directives.read().unwrap(),
+            "TRACE,wgpu=error"
+        );
+    }
+}